@@ -0,0 +1,174 @@
+use core::ops::{Add, AddAssign, SubAssign, Sub, Mul};
+
+use crate::floor;
+
+/// Returns a function that calculates the integer ceiling square root of
+/// a number, i.e. the smallest integer whose square is `>= n`.  Reuses
+/// the gradual `floor` state, since `ceil(sqrt(n))` is just
+/// `floor(sqrt(n))` bumped up by one unless `n` is itself a perfect
+/// square.  Because of that bump, `Sqrt` needs one more bit of headroom
+/// than `floor` requires: if `floor(sqrt(Num::MAX))` already equals
+/// `Sqrt::MAX`, bumping it by one overflows.
+/// ```
+/// let to_isqrt = gradual_int_sqrt::ceil::int_sqrt_gradually_changing_from::<u16, u16>(0);
+/// let result: Vec<u16> = (0u16..17).map(to_isqrt).collect();
+/// let expected: Vec<u16> = vec![
+///     // 0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16    // n
+///        0, 1, 2, 2, 2, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4    // ceil(sqrt(n))
+/// ];
+/// assert_eq!(result, expected);
+/// ```
+pub fn int_sqrt_gradually_changing_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
+    Num:  Add<Output = Num>  + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = Num> + Mul<Output = Num> + Ord,
+    Sqrt: Add<Output = Sqrt> + AddAssign + SubAssign + Copy + From<u8> + Into<Num>
+{
+    let mut to_floor = floor::int_sqrt_gradually_changing_from::<Num, Sqrt>(init);
+    move |n: Num| {
+        let floor_sqrt: Sqrt = to_floor(n);
+        let s: Num = floor_sqrt.into();
+        if s * s == n {
+            floor_sqrt
+        } else {
+            let mut ceil_sqrt = floor_sqrt;
+            ceil_sqrt += 1.into();
+            ceil_sqrt
+        }
+    }
+}
+
+/// Returns a function that calculates the integer ceiling square root of
+/// a number.  This version assumes that it will be called with
+/// increasing values; if you call it with a lower value, the previous
+/// ceil(sqrt) will be returned again.  As with
+/// [`int_sqrt_gradually_changing_from`], `Sqrt` needs one more bit of
+/// headroom than `floor` requires, to leave room for the bump-by-one.
+/// ```
+/// let to_isqrt = gradual_int_sqrt::ceil::int_sqrt_gradually_ascending_from::<u16, u16>(0);
+/// let result: Vec<u16> = (0u16..17).map(to_isqrt).collect();
+/// let expected: Vec<u16> = vec![
+///     // 0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16    // n
+///        0, 1, 2, 2, 2, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4    // ceil(sqrt(n))
+/// ];
+/// assert_eq!(result, expected);
+/// ```
+pub fn int_sqrt_gradually_ascending_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
+    Num:  Add<Output = Num>  + AddAssign + Copy + From<u8> + Mul<Output = Num> + Ord,
+    Sqrt: Add<Output = Sqrt> + AddAssign + Copy + From<u8> + Into<Num>
+{
+    let mut to_floor = floor::int_sqrt_gradually_ascending_from::<Num, Sqrt>(init);
+    move |n: Num| {
+        let floor_sqrt: Sqrt = to_floor(n);
+        let s: Num = floor_sqrt.into();
+        if s * s == n {
+            floor_sqrt
+        } else {
+            let mut ceil_sqrt = floor_sqrt;
+            ceil_sqrt += 1.into();
+            ceil_sqrt
+        }
+    }
+}
+
+/// Returns a function that calculates the integer ceiling square root of
+/// a number.  This version assumes that it will be called with
+/// decreasing values; if you call it with a higher value, the previous
+/// ceil(sqrt) will be returned again.  As with
+/// [`int_sqrt_gradually_changing_from`], `Sqrt` needs one more bit of
+/// headroom than `floor` requires, to leave room for the bump-by-one.
+/// ```
+/// let to_isqrt = gradual_int_sqrt::ceil::int_sqrt_gradually_descending_from::<u16, u16>(4);
+/// let result: Vec<u16> = (0u16..17).rev().map(to_isqrt).collect();
+/// let expected: Vec<u16> = vec![
+///     // 16 15 14 13 12 11 10  9  8  7  6  5  4  3  2  1  0    // n
+///         4, 4, 4, 4, 4, 4, 4, 3, 3, 3, 3, 3, 2, 2, 2, 1, 0    // ceil(sqrt(n))
+/// ];
+/// assert_eq!(result, expected);
+/// ```
+pub fn int_sqrt_gradually_descending_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
+    Num:  Add<Output = Num>  + SubAssign + Copy + From<u8> + Mul<Output = Num> + Ord,
+    Sqrt: Add<Output = Sqrt> + SubAssign + Copy + From<u8> + Into<Num> + AddAssign
+{
+    let mut to_floor = floor::int_sqrt_gradually_descending_from::<Num, Sqrt>(init);
+    move |n: Num| {
+        let floor_sqrt: Sqrt = to_floor(n);
+        let s: Num = floor_sqrt.into();
+        if s * s == n {
+            floor_sqrt
+        } else {
+            let mut ceil_sqrt = floor_sqrt;
+            ceil_sqrt += 1.into();
+            ceil_sqrt
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+extern crate more_asserts;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use more_asserts::*;
+
+    #[test]
+    fn test_asc_u16_u8() {
+        let to_isqrt = int_sqrt_gradually_ascending_from::<u16, u8>(0);
+        let result: Vec<u8> = (0u16..17)
+            .map(to_isqrt)
+            .collect();
+        let expected: Vec<u8> = vec![
+            // 0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16    // n
+               0, 1, 2, 2, 2, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4    // ceil(sqrt(n))
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_desc_u16_u8() {
+        let to_isqrt = int_sqrt_gradually_descending_from::<u16, u8>(4);
+        let result: Vec<u8> = (0u16..17)
+            .rev()
+            .map(to_isqrt)
+            .collect();
+        let expected: Vec<u8> = vec![
+            // 16 15 14 13 12 11 10  9  8  7  6  5  4  3  2  1  0    // n
+                4, 4, 4, 4, 4, 4, 4, 3, 3, 3, 3, 3, 2, 2, 2, 1, 0    // ceil(sqrt(n))
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_u16_u8() {
+        let to_isqrt = int_sqrt_gradually_changing_from::<u16, u8>(0);
+        let result: Vec<u8> = (0u16..17)
+            .chain((0u16..17).rev())
+            .map(to_isqrt)
+            .collect();
+        let expected: Vec<u8> = vec![
+            0, 1, 2, 2, 2, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4,
+            4, 4, 4, 4, 4, 4, 4, 3, 3, 3, 3, 3, 2, 2, 2, 1, 0
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_entire_asc_range_u16_u16() {
+        // Note that ceil needs extra width beyond what floor needs: the
+        // bump-by-one near Num::MAX would overflow a Sqrt as narrow as u8.
+        let mut to_isqrt = int_sqrt_gradually_ascending_from::<u16, u16>(0);
+        for n in 0u16..=u16::MAX {  // For every possible u16 value ...
+            let s = to_isqrt(n);
+            let t = s as u32;
+            let n = n as u32;
+            assert_ge!(t*t, n);         // the ceil is not too low:  ceil(n)^2 >= n
+            if t > 0 {
+                let t = t - 1;
+                assert_lt!(t*t, n);     // the ceil is not too high: (ceil(n) - 1)^2 < n
+            }
+        }
+    }
+
+}
@@ -1,5 +1,4 @@
-use std::ops::{Add, Sub, Mul, AddAssign, SubAssign};
-use std::fmt::Debug;
+use core::ops::{Add, Sub, Mul, AddAssign, SubAssign, Shl, Shr};
 
 //   s   lo      hi     s*s
 //  --   --      --     ---
@@ -40,6 +39,48 @@ impl MaxValue<u32>  for u32  { const MAX: u32  =  u32::MAX; }
 impl MaxValue<u64>  for u64  { const MAX: u64  =  u64::MAX; }
 impl MaxValue<u128> for u128 { const MAX: u128 = u128::MAX; }
 
+/// Gives the bit width of an integer type.  Used to seed the
+/// digit-by-digit isqrt fallback (see `isqrt_digit_by_digit`) with the
+/// largest power of four that could possibly be needed.
+trait BitWidth {
+    const BITS: u32;
+}
+impl BitWidth for u8   { const BITS: u32 = 8; }
+impl BitWidth for u16  { const BITS: u32 = 16; }
+impl BitWidth for u32  { const BITS: u32 = 32; }
+impl BitWidth for u64  { const BITS: u32 = 64; }
+impl BitWidth for u128 { const BITS: u32 = 128; }
+
+/// Computes floor(sqrt(n)) from scratch using the standard binary
+/// digit-by-digit method, which is O(log n) regardless of how far `n`
+/// is from any previously known root.  Used by the `*_with_reseed`
+/// constructors to recover in bounded time from a big jump, instead of
+/// stepping the gradual `sqrt` up or down one unit at a time.  The
+/// caller still needs one ordinary increment step afterwards to turn
+/// this floor root into the nearest one, since `n` may sit just past
+/// the midpoint between `res` and `res + 1`.
+fn isqrt_digit_by_digit<T>(mut n: T) -> T where
+    T: Copy + From<u8> + SubAssign + Ord + Add<Output = T> + Shl<u32, Output = T> + Shr<u32, Output = T> + BitWidth,
+{
+    let zero: T = 0.into();
+    let mut res: T = zero;
+    let mut bit: T = T::from(1u8) << (T::BITS - 2);
+    while bit > n {
+        bit = bit >> 2;
+    }
+    while bit != zero {
+        let cand: T = res + bit;
+        if n >= cand {
+            n -= cand;
+            res = (res >> 1) + bit;
+        } else {
+            res = res >> 1;
+        }
+        bit = bit >> 2;
+    }
+    res
+}
+
 /// Returns a function that calculates the integer square root of a number.
 /// The returned function can very efficiently produce such a square root
 /// if the input value is near the previous input value (or the init value,
@@ -56,18 +97,114 @@ impl MaxValue<u128> for u128 { const MAX: u128 = u128::MAX; }
 /// assert_eq!(result, expected);
 /// ```
 pub fn int_sqrt_gradually_changing_from<T>(init: T) -> impl FnMut(T) -> T where
-    T: Debug + Add<Output = T> + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = T> + CheckedMul<Output = T> + Ord + MaxValue<T>,
+    T: Add<Output = T> + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = T> + Mul<Output = T> + Ord + MaxValue<T>,
 {
     let mut sqrt: T = init; // the current square root
     let s: T = init.into();
     let mut lo: T = s * s;
     let mut hi: T =
-        match s.checked_mul(s) {
-            None => T::MAX,
-            Some(p) => p + s
+        if sqrt == T::MAX {
+            T::MAX
+        } else {
+            s * s + s
         };
     move |n: T| {
-        println!("{:?}: ({:?}, {:?}, {:?})", sqrt, lo, n, hi);
+        // If the current sqrt doesn't work for this n,
+        // increment/decrement it until it does.
+        if n > hi {
+            while n > hi {
+                sqrt += 1.into();
+                let s: T = sqrt.into();
+                lo = hi + 1.into();
+                hi += s + s;
+            }
+        } else {
+            while n < lo {
+                sqrt -= 1.into();
+                let s: T = sqrt.into();
+                hi = lo - 1.into();
+                lo =
+                    if hi == 0.into() {
+                        hi
+                    } else {
+                        hi - s - s + 1.into()
+                    };
+            }
+        }
+        sqrt
+    }
+}
+
+/// Like [`int_sqrt_gradually_changing_from`], but guards against the
+/// O(distance) worst case when `n` jumps far from the previous value:
+/// if the gap between `n` and the current `lo`/`hi` interval exceeds the
+/// current `sqrt`, the root is recomputed directly with the
+/// digit-by-digit method (O(log n)), the gradual state is reseeded from
+/// that floor root, and the ordinary one-step loop below then nudges it
+/// up to the nearer root if `n` is past the midpoint.
+pub fn int_sqrt_gradually_changing_from_with_reseed<T>(init: T) -> impl FnMut(T) -> T where
+    T: Add<Output = T> + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = T> + Mul<Output = T> + Ord + MaxValue<T> + Shl<u32, Output = T> + Shr<u32, Output = T> + BitWidth,
+{
+    let mut sqrt: T = init; // the current square root
+    let s: T = init.into();
+    let mut lo: T = s * s;
+    let mut hi: T =
+        if sqrt == T::MAX {
+            T::MAX
+        } else {
+            s * s + s
+        };
+    move |n: T| {
+        let s: T = sqrt.into();
+        if (n > hi && n - hi > s) || (n < lo && lo - n > s) {
+            sqrt = isqrt_digit_by_digit(n);
+            let s: T = sqrt.into();
+            lo = if sqrt == 0.into() { 0.into() } else { s * s - s + 1.into() };
+            hi = if sqrt == T::MAX { T::MAX } else { s * s + s };
+        }
+        // If the current sqrt doesn't work for this n,
+        // increment/decrement it until it does.
+        if n > hi {
+            while n > hi {
+                sqrt += 1.into();
+                let s: T = sqrt.into();
+                lo = hi + 1.into();
+                hi += s + s;
+            }
+        } else {
+            while n < lo {
+                sqrt -= 1.into();
+                let s: T = sqrt.into();
+                hi = lo - 1.into();
+                lo =
+                    if hi == 0.into() {
+                        hi
+                    } else {
+                        hi - s - s + 1.into()
+                    };
+            }
+        }
+        sqrt
+    }
+}
+
+/// Like [`int_sqrt_gradually_changing_from`], but for input types that
+/// are only `PartialOrd` (such as `f32`/`f64`), letting callers feed
+/// raw sensor-magnitude samples straight in without pre-scaling and
+/// truncating them to an integer.  An input that doesn't compare to
+/// the current bounds (e.g. NaN) is ignored and the previous `sqrt` is
+/// returned unchanged.
+pub fn int_sqrt_gradually_changing_from_partial<T>(init: T) -> impl FnMut(T) -> T where
+    T: Add<Output = T> + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = T> + Mul<Output = T> + PartialOrd,
+{
+    let mut sqrt: T = init; // the current square root
+    let s: T = init.into();
+    let mut lo: T = s * s;
+    let mut hi: T = s * s + s;
+    move |n: T| {
+        if n.partial_cmp(&hi).is_none() {
+            return sqrt;
+        }
         // If the current sqrt doesn't work for this n,
         // increment/decrement it until it does.
         if n > hi {
@@ -110,7 +247,36 @@ pub fn int_sqrt_gradually_changing_from<T>(init: T) -> impl FnMut(T) -> T where
 /// assert_eq!(result, expected);
 /// ```
 pub fn int_sqrt_gradually_ascending_from<T>(init: T) -> impl FnMut(T) -> T where
-    T: Debug + Add<Output = T>  + AddAssign + Copy + From<u8> + Mul<Output = T> + Ord + MaxValue<T>,
+    T: Add<Output = T>  + AddAssign + Copy + From<u8> + Mul<Output = T> + Ord + MaxValue<T>,
+{
+    let mut sqrt: T = init; // the current square root
+    let s: T = init.into();
+    let mut hi: T =
+        if sqrt == T::MAX {
+            T::MAX
+        } else {
+            s * s + s
+        };
+    move |n: T| {
+        // If the current sqrt doesn't work for this n,
+        // increment it until it does.
+        while n > hi {
+            sqrt += 1.into();
+            let s: T = sqrt.into();
+            hi += s + s;
+        }
+        sqrt
+    }
+}
+
+/// Like [`int_sqrt_gradually_ascending_from`], but guards against the
+/// O(distance) worst case when `n` jumps far ahead of the previous
+/// value: if the gap between `n` and `hi` exceeds the current `sqrt`,
+/// the root is recomputed directly with the digit-by-digit method, and
+/// the ordinary one-step loop below nudges it up to the nearer root if
+/// `n` is past the midpoint.
+pub fn int_sqrt_gradually_ascending_from_with_reseed<T>(init: T) -> impl FnMut(T) -> T where
+    T: Add<Output = T> + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = T> + Mul<Output = T> + Ord + MaxValue<T> + Shl<u32, Output = T> + Shr<u32, Output = T> + BitWidth,
 {
     let mut sqrt: T = init; // the current square root
     let s: T = init.into();
@@ -121,6 +287,37 @@ pub fn int_sqrt_gradually_ascending_from<T>(init: T) -> impl FnMut(T) -> T where
             s * s + s
         };
     move |n: T| {
+        let s: T = sqrt.into();
+        if n > hi && n - hi > s {
+            sqrt = isqrt_digit_by_digit(n);
+            let s: T = sqrt.into();
+            hi = if sqrt == T::MAX { T::MAX } else { s * s + s };
+        }
+        // If the current sqrt doesn't work for this n,
+        // increment it until it does.
+        while n > hi {
+            sqrt += 1.into();
+            let s: T = sqrt.into();
+            hi += s + s;
+        }
+        sqrt
+    }
+}
+
+/// Like [`int_sqrt_gradually_ascending_from`], but for input types that
+/// are only `PartialOrd` (such as `f32`/`f64`).  An input that doesn't
+/// compare to `hi` (e.g. NaN) is ignored and the previous `sqrt` is
+/// returned unchanged.
+pub fn int_sqrt_gradually_ascending_from_partial<T>(init: T) -> impl FnMut(T) -> T where
+    T: Add<Output = T>  + AddAssign + Copy + From<u8> + Mul<Output = T> + PartialOrd,
+{
+    let mut sqrt: T = init; // the current square root
+    let s: T = init.into();
+    let mut hi: T = s * s + s;
+    move |n: T| {
+        if n.partial_cmp(&hi).is_none() {
+            return sqrt;
+        }
         // If the current sqrt doesn't work for this n,
         // increment it until it does.
         while n > hi {
@@ -148,7 +345,7 @@ pub fn int_sqrt_gradually_ascending_from<T>(init: T) -> impl FnMut(T) -> T where
 /// assert_eq!(result, expected);
 /// ```
 pub fn int_sqrt_gradually_descending_from<T>(init: T) -> impl FnMut(T) -> T where
-    T: Debug + Add<Output = T> + SubAssign + Copy + From<u8> + Mul<Output = T> + Sub<Output = T> + Ord + MaxValue<T>,
+    T: Add<Output = T> + SubAssign + Copy + From<u8> + Mul<Output = T> + Sub<Output = T> + Ord + MaxValue<T>,
 {
     let mut sqrt: T = init;   // the current square root
     let s: T = init.into();
@@ -175,6 +372,83 @@ pub fn int_sqrt_gradually_descending_from<T>(init: T) -> impl FnMut(T) -> T wher
     }
 }
 
+/// Like [`int_sqrt_gradually_descending_from`], but guards against the
+/// O(distance) worst case when `n` jumps far below the previous value:
+/// if the gap between `n` and `lo` exceeds the current `sqrt`, the root
+/// is recomputed directly with the digit-by-digit method, and that
+/// floor root is bumped up by one if `n` is past the midpoint.
+pub fn int_sqrt_gradually_descending_from_with_reseed<T>(init: T) -> impl FnMut(T) -> T where
+    T: Add<Output = T> + SubAssign + Copy + From<u8> + Mul<Output = T> + Sub<Output = T> + Ord + MaxValue<T> + Shl<u32, Output = T> + Shr<u32, Output = T> + BitWidth,
+{
+    let mut sqrt: T = init;   // the current square root
+    let s: T = init.into();
+    let mut lo: T =
+        if sqrt == 0.into() {
+            0.into()
+        } else {
+            s * s - s + 1.into()
+        };
+    move |n: T| {
+        let s: T = sqrt.into();
+        if n < lo && lo - n > s {
+            sqrt = isqrt_digit_by_digit(n);
+            let s: T = sqrt.into();
+            if n > s * s + s {
+                // Past the midpoint: the nearer root is one higher.
+                sqrt = sqrt + 1.into();
+            }
+            let s: T = sqrt.into();
+            lo = if sqrt == 0.into() { 0.into() } else { s * (s - 1.into()) + 1.into() };
+        }
+        // If the current sqrt doesn't work for this n,
+        // decrement it until it does.
+        while n < lo {
+            sqrt -= 1.into();
+            if sqrt == 0.into() {
+                lo = 0.into();
+            } else {
+                let s: T = sqrt.into();
+                lo -= s + s;
+            }
+        }
+        sqrt
+    }
+}
+
+/// Like [`int_sqrt_gradually_descending_from`], but for input types
+/// that are only `PartialOrd` (such as `f32`/`f64`).  An input that
+/// doesn't compare to `lo` (e.g. NaN) is ignored and the previous
+/// `sqrt` is returned unchanged.
+pub fn int_sqrt_gradually_descending_from_partial<T>(init: T) -> impl FnMut(T) -> T where
+    T: Add<Output = T> + SubAssign + Copy + From<u8> + Mul<Output = T> + Sub<Output = T> + PartialOrd,
+{
+    let mut sqrt: T = init;   // the current square root
+    let s: T = init.into();
+    let mut lo: T =
+        if sqrt == 0.into() {
+            0.into()
+        } else {
+            s * s - s + 1.into()
+        };
+    move |n: T| {
+        if n.partial_cmp(&lo).is_none() {
+            return sqrt;
+        }
+        // If the current sqrt doesn't work for this n,
+        // decrement it until it does.
+        while n < lo {
+            sqrt -= 1.into();
+            if sqrt == 0.into() {
+                lo = 0.into();
+            } else {
+                let s: T = sqrt.into();
+                lo -= s + s;
+            }
+        }
+        sqrt
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
@@ -276,19 +550,55 @@ mod tests {
         assert_eq!(result, expected);
     }
 
-    /*
-    // Float types don't implement Ord.  Could make a PartialOrd version.
     #[test]
-    fn test_f32_u16() {
-        let to_isqrt = int_sqrt_gradually_ascending_from::<f32, u16>(0);
-        let result: Vec<u16> = (0f32..10f32).map(to_isqrt).collect();
-        let expected: Vec<u16> = vec![
-            //1 2 3 4 5 6 7 8 9 9 8 7 6 5 4 3 2 1 0
-            0,1,1,1,2,2,2,2,2,3,3,2,2,2,2,2,1,1,1,0
+    fn test_reseed_big_jump() {
+        let mut to_isqrt = int_sqrt_gradually_changing_from_with_reseed::<u32>(0);
+        assert_eq!(to_isqrt(9), 3);
+        assert_eq!(to_isqrt(1_000_293), 1000);  // far jump: must not loop 997 times
+        assert_eq!(to_isqrt(9), 3);              // far jump back down
+    }
+
+    #[test]
+    fn test_reseed_matches_gradual() {
+        // The reseeding variant must agree with the plain gradual one
+        // everywhere, reseed or no reseed.
+        let mut plain = int_sqrt_gradually_changing_from::<u32>(0);
+        let mut reseeded = int_sqrt_gradually_changing_from_with_reseed::<u32>(0);
+        for n in (0u32..2000).chain((500_000u32..500_050).rev()).chain(0u32..5) {
+            assert_eq!(reseeded(n), plain(n));
+        }
+    }
+
+    #[test]
+    fn test_reseed_asc_desc_big_jump() {
+        let mut asc = int_sqrt_gradually_ascending_from_with_reseed::<u32>(0);
+        assert_eq!(asc(1_000_293), 1000);
+
+        let mut desc = int_sqrt_gradually_descending_from_with_reseed::<u32>(1_000);
+        assert_eq!(desc(9), 3);
+    }
+
+    #[test]
+    fn test_f32() {
+        // Float types don't implement Ord, so the `_partial` constructors
+        // take PartialOrd inputs instead, letting raw sensor samples
+        // (e.g. isqrt(x^2+y^2)) feed straight in without pre-quantizing.
+        let mut to_isqrt = int_sqrt_gradually_ascending_from_partial::<f32>(0.0);
+        let result: Vec<f32> = (0u8..17).map(|n| to_isqrt(n as f32)).collect();
+        let expected: Vec<f32> = vec![
+            // 0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16     // n
+               0., 1., 1., 2., 2., 2., 2., 3., 3., 3., 3., 3., 3., 4., 4., 4., 4.     // isqrt(n)
         ];
         assert_eq!(result, expected);
     }
-     */
+
+    #[test]
+    fn test_partial_nan_keeps_previous() {
+        let mut to_isqrt = int_sqrt_gradually_changing_from_partial::<f32>(0.0);
+        assert_eq!(to_isqrt(3.0), 2.0);
+        assert_eq!(to_isqrt(f32::NAN), 2.0);
+        assert_eq!(to_isqrt(0.0), 0.0);
+    }
 
 }
 
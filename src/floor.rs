@@ -1,5 +1,54 @@
-use std::ops::{Add, Sub, Mul, AddAssign, SubAssign};
-use std::fmt::Debug;
+use core::ops::{Add, Sub, Mul, AddAssign, SubAssign, Shl, Shr};
+
+/// Gives the bit width of an integer type.  Used to seed the
+/// digit-by-digit isqrt fallback (see `isqrt_digit_by_digit`) with the
+/// largest power of four that could possibly be needed.
+trait BitWidth {
+    const BITS: u32;
+}
+impl BitWidth for u8   { const BITS: u32 = 8; }
+impl BitWidth for u16  { const BITS: u32 = 16; }
+impl BitWidth for u32  { const BITS: u32 = 32; }
+impl BitWidth for u64  { const BITS: u32 = 64; }
+impl BitWidth for u128 { const BITS: u32 = 128; }
+impl BitWidth for i8   { const BITS: u32 = 8; }
+impl BitWidth for i16  { const BITS: u32 = 16; }
+impl BitWidth for i32  { const BITS: u32 = 32; }
+impl BitWidth for i64  { const BITS: u32 = 64; }
+impl BitWidth for i128 { const BITS: u32 = 128; }
+
+/// Computes floor(sqrt(n)) from scratch using the standard binary
+/// digit-by-digit method, which is O(log n) regardless of how far `n`
+/// is from any previously known root.  Used by the `*_with_reseed`
+/// constructors to recover in bounded time from a big jump, instead of
+/// stepping the gradual `sqrt` up or down one unit at a time.
+fn isqrt_digit_by_digit<Num, Sqrt>(mut n: Num) -> Sqrt where
+    Num:  Copy + From<u8> + SubAssign + Ord + BitWidth + Add<Output = Num> + Shl<u32, Output = Num> + Shr<u32, Output = Num>,
+    Sqrt: TryFrom<Num>,
+{
+    let zero: Num = 0.into();
+    let mut res: Num = zero;
+    let mut bit: Num = Num::from(1u8) << (Num::BITS - 2);
+    while bit > n {
+        bit = bit >> 2;
+    }
+    while bit != zero {
+        let cand: Num = res + bit;
+        if n >= cand {
+            n -= cand;
+            res = (res >> 1) + bit;
+        } else {
+            res = res >> 1;
+        }
+        bit = bit >> 2;
+    }
+    // res is sqrt(n), which fits in Sqrt by the crate's width invariant
+    // (Sqrt at least half as wide as Num), so the narrowing can't fail.
+    match res.try_into() {
+        Ok(sqrt) => sqrt,
+        Err(_) => unreachable!(),
+    }
+}
 
 /// Returns a function that calculates the integer square root of a number.
 /// The returned function can very efficiently produce such a square root
@@ -17,8 +66,8 @@ use std::fmt::Debug;
 /// assert_eq!(result, expected);
 /// ```
 pub fn int_sqrt_gradually_changing_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
-    Num:  Debug + Add<Output = Num>  + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = Num> + Mul<Output = Num> + Ord,
-    Sqrt: Debug + Add<Output = Sqrt> + AddAssign + SubAssign + Copy + From<u8> + Into<Num>
+    Num:  Add<Output = Num>  + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = Num> + Mul<Output = Num> + Ord,
+    Sqrt: Add<Output = Sqrt> + AddAssign + SubAssign + Copy + From<u8> + Into<Num>
 {
     let mut sqrt: Sqrt = init; // the current square root
     let s: Num = init.into();
@@ -46,6 +95,149 @@ pub fn int_sqrt_gradually_changing_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num
     }
 }
 
+/// Like [`int_sqrt_gradually_changing_from`], but guards against the
+/// O(distance) worst case when `n` jumps far from the previous value:
+/// if the gap between `n` and the current `lo`/`hi` interval exceeds the
+/// current `sqrt`, the root is recomputed directly with the
+/// digit-by-digit method (O(log n)) instead of stepping one unit at a
+/// time, and the gradual state is reseeded from that result.
+/// ```
+/// let mut to_isqrt = gradual_int_sqrt::floor::int_sqrt_gradually_changing_from_with_reseed::<u32, u16>(0);
+/// assert_eq!(to_isqrt(9), 3);
+/// assert_eq!(to_isqrt(1_000_293), 1000);
+/// assert_eq!(to_isqrt(9), 3);
+/// ```
+pub fn int_sqrt_gradually_changing_from_with_reseed<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
+    Num:  Copy + From<u8> + SubAssign + AddAssign + Sub<Output = Num> + Mul<Output = Num> + Add<Output = Num> + Ord + BitWidth + Shl<u32, Output = Num> + Shr<u32, Output = Num>,
+    Sqrt: Copy + From<u8> + AddAssign + SubAssign + Into<Num> + TryFrom<Num>
+{
+    let mut sqrt: Sqrt = init; // the current square root
+    let s: Num = init.into();
+    let mut lo: Num = s * s;
+    let mut hi: Num = lo + (s * 2.into());   // (s + 1)^2 - 1 without overflowing
+    move |n: Num| {
+        let s: Num = sqrt.into();
+        if (n > hi && n - hi > s) || (n < lo && lo - n > s) {
+            sqrt = isqrt_digit_by_digit(n);
+            let s: Num = sqrt.into();
+            lo = s * s;
+            hi = lo + s + s;
+        } else if n > hi {
+            while n > hi {
+                sqrt += 1.into();
+                let s: Num = sqrt.into();
+                lo = hi + 1.into();
+                hi = lo + s + s;
+            }
+        } else {
+            while n < lo {
+                sqrt -= 1.into();
+                let s: Num = sqrt.into();
+                hi = lo - 1.into();
+                lo = hi - s - s;
+            }
+        }
+        sqrt
+    }
+}
+
+/// Like [`int_sqrt_gradually_changing_from`], but for input types that
+/// are only `PartialOrd` (such as `f32`/`f64`), letting callers feed
+/// raw sensor-magnitude samples straight in without pre-scaling and
+/// truncating them to an integer.  An input that doesn't compare to
+/// the current bounds (e.g. NaN) is ignored and the previous `sqrt` is
+/// returned unchanged.
+/// ```
+/// let mut to_isqrt = gradual_int_sqrt::floor::int_sqrt_gradually_changing_from_partial::<f32, u16>(0u16);
+/// assert_eq!(to_isqrt(30.0), 5);
+/// assert_eq!(to_isqrt(f32::NAN), 5);   // incomparable input: sqrt unchanged
+/// ```
+pub fn int_sqrt_gradually_changing_from_partial<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
+    Num:  Add<Output = Num>  + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = Num> + Mul<Output = Num> + PartialOrd,
+    Sqrt: Add<Output = Sqrt> + AddAssign + SubAssign + Copy + From<u8> + Into<Num>
+{
+    let mut sqrt: Sqrt = init; // the current square root
+    let s: Num = init.into();
+    let mut lo: Num = s * s;
+    let mut hi: Num = lo + (s * 2.into());   // (s + 1)^2 - 1 without overflowing
+    move |n: Num| {
+        if n.partial_cmp(&hi).is_none() {
+            return sqrt;
+        }
+        // If the current sqrt doesn't work for this n,
+        // increment it until it does.
+        if n > hi {
+            while n > hi {
+                sqrt += 1.into();
+                let s: Num = sqrt.into();
+                lo = hi + 1.into();
+                hi = lo + s + s;
+            }
+        } else {
+            while n < lo {
+                sqrt -= 1.into();
+                let s: Num = sqrt.into();
+                hi = lo - 1.into();
+                lo = hi - s - s;
+            }
+        }
+        sqrt
+    }
+}
+
+/// Returns a function that advances `N` independent gradual isqrt
+/// lanes in lockstep, e.g. the x, y, z (and magnitude) channels of an
+/// accelerometer/gyroscope stream that all need `isqrt` every sample.
+/// Each lane runs the same increment/decrement recurrence as
+/// [`int_sqrt_gradually_changing_from`], but the state is laid out as
+/// struct-of-arrays (`[Sqrt; N]`/`[Num; N]`) instead of as N separate
+/// closures, so the branch-heavy inner loop is amortized across lanes
+/// and friendly to autovectorization, the way hardware rsqrt is
+/// applied lane-wise to a packed vector.  `N = 1` is just the scalar
+/// closure with extra array wrapping.
+/// ```
+/// let mut to_isqrt = gradual_int_sqrt::floor::int_sqrt_gradually_changing_from_lanes::<u16, u8, 3>([0, 0, 0]);
+/// assert_eq!(to_isqrt([9, 16, 25]), [3, 4, 5]);
+/// assert_eq!(to_isqrt([3, 24, 35]), [1, 4, 5]);   // each lane keeps its own state
+/// ```
+pub fn int_sqrt_gradually_changing_from_lanes<Num, Sqrt, const N: usize>(init: [Sqrt; N]) -> impl FnMut([Num; N]) -> [Sqrt; N] where
+    Num:  Add<Output = Num>  + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = Num> + Mul<Output = Num> + Ord,
+    Sqrt: Add<Output = Sqrt> + AddAssign + SubAssign + Copy + From<u8> + Into<Num>
+{
+    let mut sqrt: [Sqrt; N] = init;
+    let mut lo: [Num; N] = core::array::from_fn(|i| {
+        let s: Num = init[i].into();
+        s * s
+    });
+    let mut hi: [Num; N] = core::array::from_fn(|i| {
+        let s: Num = init[i].into();
+        lo[i] + (s * 2.into())   // (s + 1)^2 - 1 without overflowing
+    });
+    move |ns: [Num; N]| {
+        for i in 0..N {
+            let n = ns[i];
+            // If the current sqrt doesn't work for this lane,
+            // increment/decrement it until it does.
+            if n > hi[i] {
+                while n > hi[i] {
+                    sqrt[i] += 1.into();
+                    let s: Num = sqrt[i].into();
+                    lo[i] = hi[i] + 1.into();
+                    hi[i] = lo[i] + s + s;
+                }
+            } else {
+                while n < lo[i] {
+                    sqrt[i] -= 1.into();
+                    let s: Num = sqrt[i].into();
+                    hi[i] = lo[i] - 1.into();
+                    lo[i] = hi[i] - s - s;
+                }
+            }
+        }
+        sqrt
+    }
+}
+
 /// Returns a function that calculates the integer square root of a number.
 /// The returned function can very efficiently produce such a square root
 /// if the input value is near the previous input value (or the init value,
@@ -62,13 +254,68 @@ pub fn int_sqrt_gradually_changing_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num
 /// assert_eq!(result, expected);
 /// ```
 pub fn int_sqrt_gradually_ascending_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
-    Num:  Debug + Add<Output = Num>  + AddAssign + Copy + From<u8> + Mul<Output = Num> + Ord,
-    Sqrt: Debug + Add<Output = Sqrt> + AddAssign + Copy + From<u8> + Into<Num>
+    Num:  Add<Output = Num>  + AddAssign + Copy + From<u8> + Mul<Output = Num> + Ord,
+    Sqrt: Add<Output = Sqrt> + AddAssign + Copy + From<u8> + Into<Num>
+{
+    let mut sqrt: Sqrt = init; // the current square root
+    let s: Num = init.into();
+    let mut hi: Num = s * (s + 2.into());   // (s + 1)^2 - 1 without overflowing
+    move |n: Num| {
+        // If the current sqrt doesn't work for this n,
+        // increment it until it does.
+        while n > hi {
+            sqrt += 1.into();
+            let s: Num = sqrt.into();
+            hi += s + s + 1.into();
+        }
+        sqrt
+    }
+}
+
+/// Like [`int_sqrt_gradually_ascending_from`], but guards against the
+/// O(distance) worst case when `n` jumps far ahead of the previous
+/// value: if the gap between `n` and `hi` exceeds the current `sqrt`,
+/// the root is recomputed directly with the digit-by-digit method
+/// instead of stepping one unit at a time.
+pub fn int_sqrt_gradually_ascending_from_with_reseed<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
+    Num:  Add<Output = Num> + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = Num> + Mul<Output = Num> + Ord + BitWidth + Shl<u32, Output = Num> + Shr<u32, Output = Num>,
+    Sqrt: Add<Output = Sqrt> + AddAssign + Copy + From<u8> + Into<Num> + TryFrom<Num>
+{
+    let mut sqrt: Sqrt = init; // the current square root
+    let s: Num = init.into();
+    let mut hi: Num = s * (s + 2.into());   // (s + 1)^2 - 1 without overflowing
+    move |n: Num| {
+        let s: Num = sqrt.into();
+        if n > hi && n - hi > s {
+            sqrt = isqrt_digit_by_digit(n);
+            let s: Num = sqrt.into();
+            hi = s * (s + 2.into());
+        } else {
+            while n > hi {
+                sqrt += 1.into();
+                let s: Num = sqrt.into();
+                hi += s + s + 1.into();
+            }
+        }
+        sqrt
+    }
+}
+
+/// Like [`int_sqrt_gradually_ascending_from`], but for input types that
+/// are only `PartialOrd` (such as `f32`/`f64`).  An input that doesn't
+/// compare to `hi` (e.g. NaN) is ignored and the previous `sqrt` is
+/// returned unchanged.
+pub fn int_sqrt_gradually_ascending_from_partial<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
+    Num:  Add<Output = Num>  + AddAssign + Copy + From<u8> + Mul<Output = Num> + PartialOrd,
+    Sqrt: Add<Output = Sqrt> + AddAssign + Copy + From<u8> + Into<Num>
 {
     let mut sqrt: Sqrt = init; // the current square root
     let s: Num = init.into();
     let mut hi: Num = s * (s + 2.into());   // (s + 1)^2 - 1 without overflowing
     move |n: Num| {
+        if n.partial_cmp(&hi).is_none() {
+            return sqrt;
+        }
         // If the current sqrt doesn't work for this n,
         // increment it until it does.
         while n > hi {
@@ -96,8 +343,8 @@ pub fn int_sqrt_gradually_ascending_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(Nu
 /// assert_eq!(result, expected);
 /// ```
 pub fn int_sqrt_gradually_descending_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
-    Num:  Debug + Add<Output = Num>  + SubAssign + Copy + From<u8> + Mul<Output = Num> + Ord,
-    Sqrt: Debug + Add<Output = Sqrt> + SubAssign + Copy + From<u8> + Into<Num>
+    Num:  Add<Output = Num>  + SubAssign + Copy + From<u8> + Mul<Output = Num> + Ord,
+    Sqrt: Add<Output = Sqrt> + SubAssign + Copy + From<u8> + Into<Num>
 {
     let mut sqrt: Sqrt = init;   // the current square root
     let s: Num = init.into();
@@ -114,6 +361,61 @@ pub fn int_sqrt_gradually_descending_from<Num, Sqrt>(init: Sqrt) -> impl FnMut(N
     }
 }
 
+/// Like [`int_sqrt_gradually_descending_from`], but guards against the
+/// O(distance) worst case when `n` jumps far below the previous value:
+/// if the gap between `n` and `lo` exceeds the current `sqrt`, the root
+/// is recomputed directly with the digit-by-digit method instead of
+/// stepping one unit at a time.
+pub fn int_sqrt_gradually_descending_from_with_reseed<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
+    Num:  Add<Output = Num> + SubAssign + Copy + From<u8> + Sub<Output = Num> + Mul<Output = Num> + Ord + BitWidth + Shl<u32, Output = Num> + Shr<u32, Output = Num>,
+    Sqrt: Add<Output = Sqrt> + SubAssign + Copy + From<u8> + Into<Num> + TryFrom<Num>
+{
+    let mut sqrt: Sqrt = init;   // the current square root
+    let s: Num = init.into();
+    let mut lo: Num = s * s;
+    move |n: Num| {
+        let s: Num = sqrt.into();
+        if n < lo && lo - n > s {
+            sqrt = isqrt_digit_by_digit(n);
+            let s: Num = sqrt.into();
+            lo = s * s;
+        } else {
+            while n < lo {
+                sqrt -= 1.into();
+                let s: Num = sqrt.into();
+                lo -= s + s + 1.into();
+            }
+        }
+        sqrt
+    }
+}
+
+/// Like [`int_sqrt_gradually_descending_from`], but for input types
+/// that are only `PartialOrd` (such as `f32`/`f64`).  An input that
+/// doesn't compare to `lo` (e.g. NaN) is ignored and the previous
+/// `sqrt` is returned unchanged.
+pub fn int_sqrt_gradually_descending_from_partial<Num, Sqrt>(init: Sqrt) -> impl FnMut(Num) -> Sqrt where
+    Num:  Add<Output = Num>  + SubAssign + Copy + From<u8> + Mul<Output = Num> + PartialOrd,
+    Sqrt: Add<Output = Sqrt> + SubAssign + Copy + From<u8> + Into<Num>
+{
+    let mut sqrt: Sqrt = init;   // the current square root
+    let s: Num = init.into();
+    let mut lo: Num = s * s;
+    move |n: Num| {
+        if n.partial_cmp(&lo).is_none() {
+            return sqrt;
+        }
+        // If the current sqrt doesn't work for this n,
+        // decrement it until it does.
+        while n < lo {
+            sqrt -= 1.into();
+            let s: Num = sqrt.into();
+            lo -= s + s + 1.into();
+        }
+        sqrt
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
@@ -229,19 +531,87 @@ mod tests {
         assert_eq!(result, expected);
     }
 
-    /*
-    // Float types don't implement Ord.  Could make a PartialOrd version.
+    #[test]
+    fn test_reseed_big_jump() {
+        let mut to_isqrt = int_sqrt_gradually_changing_from_with_reseed::<u32, u16>(0);
+        assert_eq!(to_isqrt(9), 3);
+        assert_eq!(to_isqrt(1_000_293), 1000);  // far jump: must not loop 997 times
+        assert_eq!(to_isqrt(1_002_000), 1000);  // still gradual from here
+        assert_eq!(to_isqrt(9), 3);              // far jump back down
+    }
+
+    #[test]
+    fn test_reseed_matches_gradual() {
+        // The reseeding variant must agree with the plain gradual one
+        // everywhere, reseed or no reseed.
+        let mut plain = int_sqrt_gradually_changing_from::<u32, u16>(0);
+        let mut reseeded = int_sqrt_gradually_changing_from_with_reseed::<u32, u16>(0);
+        for n in (0u32..2000).chain((500_000u32..500_050).rev()).chain(0u32..5) {
+            assert_eq!(reseeded(n), plain(n));
+        }
+    }
+
+    #[test]
+    fn test_reseed_asc_desc_big_jump() {
+        let mut asc = int_sqrt_gradually_ascending_from_with_reseed::<u32, u16>(0);
+        assert_eq!(asc(1_000_293), 1000);
+
+        let mut desc = int_sqrt_gradually_descending_from_with_reseed::<u32, u16>(1_000);
+        assert_eq!(desc(9), 3);
+    }
+
     #[test]
     fn test_f32_u16() {
-        let to_isqrt = int_sqrt_gradually_ascending_from::<f32, u16>(0);
-        let result: Vec<u16> = (0f32..10f32).map(to_isqrt).collect();
+        // Float types don't implement Ord, so the `_partial` constructors
+        // take PartialOrd inputs instead, letting raw sensor samples
+        // (e.g. isqrt(x^2+y^2)) feed straight in without pre-quantizing.
+        let mut to_isqrt = int_sqrt_gradually_ascending_from_partial::<f32, u16>(0u16);
+        let result: Vec<u16> = (0u16..17).map(|n| to_isqrt(n as f32)).collect();
         let expected: Vec<u16> = vec![
-            //1 2 3 4 5 6 7 8 9 9 8 7 6 5 4 3 2 1 0
-            0,1,1,1,2,2,2,2,2,3,3,2,2,2,2,2,1,1,1,0
+            // 0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16     // n
+               0, 1, 1, 1, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4     // isqrt(n)
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_f32_u16_changing() {
+        let mut to_isqrt = int_sqrt_gradually_changing_from_partial::<f32, u16>(0u16);
+        let result: Vec<u16> = (0u16..10).chain((0u16..10).rev())
+            .map(|n| to_isqrt(n as f32))
+            .collect();
+        let expected: Vec<u16> = vec![
+            //1 2 3 4 5 6 7 8 9 9 8 7 6 5 4 3 2 1 0     // n
+            0,1,1,1,2,2,2,2,2,3,3,2,2,2,2,2,1,1,1,0     // isqrt(n)
         ];
         assert_eq!(result, expected);
     }
-     */
+
+    #[test]
+    fn test_partial_nan_keeps_previous() {
+        let mut to_isqrt = int_sqrt_gradually_changing_from_partial::<f32, u16>(0u16);
+        assert_eq!(to_isqrt(30.0), 5);
+        assert_eq!(to_isqrt(f32::NAN), 5);
+        assert_eq!(to_isqrt(0.0), 0);
+    }
+
+    #[test]
+    fn test_lanes_u16_u8() {
+        let mut to_isqrt = int_sqrt_gradually_changing_from_lanes::<u16, u8, 3>([0, 0, 0]);
+        assert_eq!(to_isqrt([9, 16, 25]), [3, 4, 5]);
+        assert_eq!(to_isqrt([3, 24, 35]), [1, 4, 5]);   // each lane keeps its own state
+        assert_eq!(to_isqrt([0, 0, 0]), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_lanes_matches_scalar() {
+        // Lane 0 must track the same sequence as the plain scalar closure.
+        let mut scalar = int_sqrt_gradually_changing_from::<u16, u8>(0);
+        let mut lanes = int_sqrt_gradually_changing_from_lanes::<u16, u8, 1>([0]);
+        for n in (0u16..20).chain((0u16..20).rev()) {
+            assert_eq!(lanes([n]), [scalar(n)]);
+        }
+    }
 
 }
 
@@ -89,6 +89,10 @@
 //! with an appropriate initial isqrt value.
 //!
 
+#![cfg_attr(not(test), no_std)]
+
+pub mod ceil;
 pub mod closest;
 pub mod floor;
+pub mod rational;
 
@@ -0,0 +1,119 @@
+use core::ops::{Add, Sub, Mul, Div, AddAssign, SubAssign};
+
+use crate::floor;
+
+/// Returns a function that maintains a rational approximation `(h, k)`
+/// to `sqrt(n)` for a gradually-changing stream of `n`, i.e.
+/// `sqrt(n) ≈ h / k`.  This gives more precision than a single integer
+/// isqrt without the overflow risk of scaling the input by a power of
+/// two to gain resolution.
+///
+/// The floor isqrt `a0` of `n` still comes cheaply out of an internal
+/// gradual `floor` closure; from that, `(h, k)` is refined via the
+/// continued-fraction expansion of `sqrt(n)`, with `m0 = 0`, `d0 = 1`,
+/// and
+/// ```text
+/// m_{k+1} = d_k * a_k - m_k
+/// d_{k+1} = (n - m_{k+1}^2) / d_k
+/// a_{k+1} = floor((a0 + m_{k+1}) / d_{k+1})
+/// ```
+/// accumulating convergents `h_k = a_k * h_{k-1} + h_{k-2}` and
+/// `k_k = a_k * k_{k-1} + k_{k-2}` (seeded with `h_{-1} = 1, h_{-2} = 0,
+/// k_{-1} = 0, k_{-2} = 1`).  `depth` bounds how many convergent steps
+/// are taken per sample, so only that fixed amount of cheap integer
+/// arithmetic runs on top of the gradual floor isqrt.
+/// ```
+/// let mut to_isqrt = gradual_int_sqrt::rational::int_sqrt_rational_gradually_changing_from::<u32>(0, 2);
+/// let (h, k) = to_isqrt(30);
+/// // sqrt(30) ~= 5.477; h/k should land close to that.
+/// let approx = h as f64 / k as f64;
+/// assert!((approx - 30f64.sqrt()).abs() < 0.01);
+/// ```
+pub fn int_sqrt_rational_gradually_changing_from<T>(init: T, depth: u32) -> impl FnMut(T) -> (T, T) where
+    T: Add<Output = T> + AddAssign + SubAssign + Copy + From<u8> + Sub<Output = T> + Mul<Output = T> + Div<Output = T> + Ord,
+{
+    let mut to_isqrt = floor::int_sqrt_gradually_changing_from::<T, T>(init);
+    move |n: T| {
+        let a0: T = to_isqrt(n);
+        convergent(n, a0, depth)
+    }
+}
+
+/// Computes the `depth`-th continued-fraction convergent `(h, k)` of
+/// `sqrt(n)`, given the floor isqrt `a0` of `n` as the zeroth partial
+/// quotient.
+fn convergent<T>(n: T, a0: T, depth: u32) -> (T, T) where
+    T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T> + Copy + From<u8> + PartialEq,
+{
+    let zero: T = 0.into();
+    let one: T = 1.into();
+
+    let mut m: T = zero;
+    let mut d: T = one;
+    let mut a: T = a0;
+
+    let (mut h2, mut h1): (T, T) = (zero, one);  // h_{-2}, h_{-1}
+    let (mut k2, mut k1): (T, T) = (one, zero);  // k_{-2}, k_{-1}
+
+    let mut h: T = a * h1 + h2;  // h_0
+    let mut k: T = a * k1 + k2;  // k_0
+    h2 = h1; h1 = h;
+    k2 = k1; k1 = k;
+
+    for _ in 0..depth {
+        let m_next = d * a - m;
+        let d_next = (n - m_next * m_next) / d;
+        if d_next == zero {
+            // n is a perfect square: the continued fraction has already
+            // terminated exactly, so there are no further convergents.
+            break;
+        }
+        let a_next = (a0 + m_next) / d_next;
+
+        h = a_next * h1 + h2;
+        k = a_next * k1 + k2;
+
+        m = m_next;
+        d = d_next;
+        a = a_next;
+        h2 = h1; h1 = h;
+        k2 = k1; k1 = k;
+    }
+
+    (h, k)
+}
+
+///////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_convergent_matches_sqrt() {
+        for &n in &[2u64, 3, 30, 1000, 1_000_293] {
+            let a0 = (n as f64).sqrt().floor() as u64;
+            let (h, k) = convergent(n, a0, 8);
+            let approx = h as f64 / k as f64;
+            assert!((approx - (n as f64).sqrt()).abs() < 1e-3, "n={} h={} k={}", n, h, k);
+        }
+    }
+
+    #[test]
+    fn test_perfect_square_is_exact() {
+        let (h, k) = convergent(25u64, 5, 3);
+        assert_eq!(h, 5 * k);
+    }
+
+    #[test]
+    fn test_gradual_stream() {
+        let mut to_rational = int_sqrt_rational_gradually_changing_from::<u32>(0, 6);
+        for n in (0u32..20).chain((0u32..20).rev()) {
+            let (h, k) = to_rational(n);
+            let approx = h as f64 / k as f64;
+            assert!((approx - (n as f64).sqrt()).abs() < 0.01, "n={} h={} k={}", n, h, k);
+        }
+    }
+
+}